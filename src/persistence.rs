@@ -1,86 +1,278 @@
+use std::fmt;
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write};
 use std::sync::Arc;
-use std::time::Instant;
-use lightning::routing::gossip::NetworkGraph;
+use std::time::{Duration, Instant};
+use lightning::routing::gossip::{ChannelAnnouncement, ChannelUpdate, NetworkGraph};
 use lightning::util::ser::Writeable;
-use tokio::sync::mpsc;
+use native_tls::{Certificate, Identity, TlsConnector};
+use postgres_native_tls::MakeTlsConnector;
+use tokio::sync::{mpsc, watch};
+use tokio_postgres::types::ToSql;
 use tokio_postgres::NoTls;
 
 use crate::{config, hex_utils, TestLogger};
 use crate::types::GossipMessage;
 
+/// Builds the TLS connector used to reach Postgres when `config::postgres_tls_enabled()` is set,
+/// picking up an optional CA certificate, an optional client certificate/key pair, and an
+/// sslmode-style toggle for certificate/hostname verification. A misconfigured cert/key is a
+/// config problem, not a transient DB error, but it still shouldn't panic the persister — it's
+/// surfaced as a `PersistenceError` like the database errors in this module.
+fn build_tls_connector() -> Result<MakeTlsConnector, PersistenceError> {
+	let mut builder = TlsConnector::builder();
+
+	if let Some(ca_cert_path) = config::postgres_tls_ca_cert_path() {
+		let ca_cert = std::fs::read(&ca_cert_path)
+			.map_err(|e| PersistenceError::Tls(format!("failed to read TLS CA certificate at {}: {}", ca_cert_path, e)))?;
+		let ca_cert = Certificate::from_pem(&ca_cert)
+			.map_err(|e| PersistenceError::Tls(format!("invalid TLS CA certificate at {}: {}", ca_cert_path, e)))?;
+		builder.add_root_certificate(ca_cert);
+	}
+
+	if let (Some(client_cert_path), Some(client_key_path)) =
+		(config::postgres_tls_client_cert_path(), config::postgres_tls_client_key_path())
+	{
+		let client_cert = std::fs::read(&client_cert_path)
+			.map_err(|e| PersistenceError::Tls(format!("failed to read TLS client certificate at {}: {}", client_cert_path, e)))?;
+		let client_key = std::fs::read(&client_key_path)
+			.map_err(|e| PersistenceError::Tls(format!("failed to read TLS client key at {}: {}", client_key_path, e)))?;
+		let identity = Identity::from_pkcs8(&client_cert, &client_key)
+			.map_err(|e| PersistenceError::Tls(format!("invalid TLS client certificate/key pair: {}", e)))?;
+		builder.identity(identity);
+	}
+
+	// an sslmode=require-style escape hatch for self-signed/staging deployments: the connection
+	// is still encrypted, but the server's certificate and hostname are not verified
+	if !config::postgres_tls_verify() {
+		builder.danger_accept_invalid_certs(true);
+		builder.danger_accept_invalid_hostnames(true);
+	}
+
+	let connector = builder.build()
+		.map_err(|e| PersistenceError::Tls(format!("failed to build Postgres TLS connector: {}", e)))?;
+	Ok(MakeTlsConnector::new(connector))
+}
+
+/// Number of pending rows that triggers an eager flush, even if the flush interval hasn't
+/// elapsed yet. Keeps memory bounded during the initial sync flood.
+const BATCH_SIZE: usize = 1000;
+/// How often buffered rows are flushed to Postgres when the batch size isn't reached first.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+/// How many times a failing statement is retried before the error is surfaced to the caller.
+const MAX_RETRIES: u32 = 5;
+/// Starting delay for the exponential backoff between retries; doubles on each attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub(crate) enum PersistenceError {
+	Database(tokio_postgres::Error),
+	Tls(String),
+}
+
+impl fmt::Display for PersistenceError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PersistenceError::Database(e) => write!(f, "database error: {}", e),
+			PersistenceError::Tls(e) => write!(f, "TLS configuration error: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for PersistenceError {}
+
+impl From<tokio_postgres::Error> for PersistenceError {
+	fn from(e: tokio_postgres::Error) -> Self {
+		PersistenceError::Database(e)
+	}
+}
+
+impl PersistenceError {
+	/// True if Postgres rejected the *contents* of one row in the batch — SQLSTATE class 22
+	/// (`data_exception`) or 23 (`integrity_constraint_violation`) — as opposed to a systemic
+	/// failure that also carries a SQLSTATE, like 53 (`insufficient_resources`: disk/memory/too
+	/// many connections), 57 (`operator_intervention`: admin shutdown, query/statement timeout),
+	/// or 40 (`transaction_rollback`: deadlock/serialization failure), or the statement never
+	/// reaching the server at all (closed/broken connection, I/O error). Only class 22/23 can be
+	/// blamed on a single bad row; everything else will fail identically no matter how the batch
+	/// is split, so it must be propagated rather than used to justify bisecting.
+	fn is_row_level(&self) -> bool {
+		matches!(self, PersistenceError::Database(e) if e.code()
+			.map_or(false, |c| c.code().starts_with("22") || c.code().starts_with("23")))
+	}
+}
+
+/// Runs `query` against `client`, retrying with exponential backoff on failure instead of
+/// immediately giving up. Transient Postgres hiccups shouldn't take down the whole persister.
+/// Row-level errors (see `PersistenceError::is_row_level`) are deterministic — the same bad row
+/// will fail identically on every attempt — so they're returned immediately instead of burning
+/// up to `MAX_RETRIES` rounds of backoff; it's the caller's job to isolate the bad row (e.g. by
+/// bisecting a batch) rather than ours to stall retrying it.
+async fn execute_with_retry(client: &tokio_postgres::Client, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, PersistenceError> {
+	let mut delay = RETRY_BASE_DELAY;
+	let mut attempt = 0;
+	loop {
+		match client.execute(query, params).await {
+			Ok(rows) => return Ok(rows),
+			Err(e) => {
+				let e = PersistenceError::from(e);
+				if e.is_row_level() || attempt >= MAX_RETRIES {
+					return Err(e);
+				}
+				attempt += 1;
+				eprintln!("db statement failed (attempt {}/{}): {}; retrying in {:?}", attempt, MAX_RETRIES, e, delay);
+				tokio::time::sleep(delay).await;
+				delay *= 2;
+			}
+		}
+	}
+}
+
+/// Runs a multi-statement `batch_execute` against `client`, retrying with exponential backoff on
+/// failure just like `execute_with_retry`. Used for the one-off DDL statements that don't take
+/// params, so a momentary blip during startup doesn't fail the whole persister with zero backoff.
+async fn batch_execute_with_retry(client: &tokio_postgres::Client, query: &str) -> Result<(), PersistenceError> {
+	let mut delay = RETRY_BASE_DELAY;
+	let mut attempt = 0;
+	loop {
+		match client.batch_execute(query).await {
+			Ok(()) => return Ok(()),
+			Err(e) if attempt < MAX_RETRIES => {
+				attempt += 1;
+				eprintln!("db statement failed (attempt {}/{}): {}; retrying in {:?}", attempt, MAX_RETRIES, e, delay);
+				tokio::time::sleep(delay).await;
+				delay *= 2;
+			}
+			Err(e) => return Err(e.into()),
+		}
+	}
+}
+
+/// Return type shared by the per-batch insert closures passed to `insert_batch_with_bisection`.
+type InsertFuture<'a> = std::pin::Pin<Box<dyn std::future::Future<Output = Result<u64, PersistenceError>> + Send + 'a>>;
+
+/// Shared bisect-and-retry control flow for a batched multi-row INSERT. `try_insert` builds and
+/// executes the statement for exactly `batch` (via `execute_with_retry`); `describe` renders an
+/// identifying label for a single row so a permanently-bad one can be logged before being
+/// dropped. If the statement fails with a row-level (SQLSTATE 22/23) error, a single malformed
+/// row in an otherwise-valid batch would otherwise sink the whole thing, so the batch is bisected
+/// and each half retried independently; a batch of one that still fails is logged and dropped
+/// rather than taking the rest of the buffer down with it. A connection-level failure is
+/// propagated immediately instead, since bisecting can't help and dropping rows would just mask
+/// an outage.
+fn insert_batch_with_bisection<'a, T, F, D>(batch: &'a [T], try_insert: &'a F, describe: &'a D) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersistenceError>> + Send + 'a>>
+where
+	T: Sync + 'a,
+	F: Fn(&'a [T]) -> InsertFuture<'a> + Sync + 'a,
+	D: Fn(&T) -> String + Sync + 'a,
+{
+	Box::pin(async move {
+		if batch.is_empty() {
+			return Ok(());
+		}
+
+		match try_insert(batch).await {
+			Ok(_) => Ok(()),
+			// the server rejected the batch's contents rather than refusing the connection —
+			// safe to isolate the bad row(s) by bisecting rather than losing everything
+			Err(e) if e.is_row_level() && batch.len() == 1 => {
+				eprintln!("dropping unpersistable {}: {}", describe(&batch[0]), e);
+				Ok(())
+			}
+			Err(e) if e.is_row_level() => {
+				let mid = batch.len() / 2;
+				insert_batch_with_bisection(&batch[..mid], try_insert, describe).await?;
+				insert_batch_with_bisection(&batch[mid..], try_insert, describe).await
+			}
+			// connection/protocol-level failure: every sub-batch would fail identically, so
+			// propagate instead of bisecting our way into silently dropping everything
+			Err(e) => Err(e),
+		}
+	})
+}
+
+/// Connects to Postgres, retrying with exponential backoff on failure. The initial connect is
+/// just as likely to hit a transient Postgres restart/network blip as any later query, so it
+/// goes through the same bounded-retry machinery instead of panicking the persister.
+async fn connect_with_retry<T>(connection_config: &tokio_postgres::Config, tls: T) -> Result<(tokio_postgres::Client, tokio_postgres::Connection<tokio_postgres::Socket, T::Stream>), PersistenceError>
+where
+	T: tokio_postgres::tls::MakeTlsConnect<tokio_postgres::Socket> + Clone,
+{
+	let mut delay = RETRY_BASE_DELAY;
+	let mut attempt = 0;
+	loop {
+		match connection_config.connect(tls.clone()).await {
+			Ok(pair) => return Ok(pair),
+			Err(e) if attempt < MAX_RETRIES => {
+				attempt += 1;
+				eprintln!("failed to connect to Postgres (attempt {}/{}): {}; retrying in {:?}", attempt, MAX_RETRIES, e, delay);
+				tokio::time::sleep(delay).await;
+				delay *= 2;
+			}
+			Err(e) => return Err(e.into()),
+		}
+	}
+}
+
 pub(crate) struct GossipPersister {
 	gossip_persistence_receiver: mpsc::Receiver<GossipMessage>,
 	server_sync_completion_sender: mpsc::Sender<()>,
 	network_graph: Arc<NetworkGraph<TestLogger>>,
+	shutdown_receiver: watch::Receiver<bool>,
 }
 
 impl GossipPersister {
-	pub fn new(server_sync_completion_sender: mpsc::Sender<()>, network_graph: Arc<NetworkGraph<TestLogger>>) -> (Self, mpsc::Sender<GossipMessage>) {
+	pub fn new(server_sync_completion_sender: mpsc::Sender<()>, network_graph: Arc<NetworkGraph<TestLogger>>) -> (Self, mpsc::Sender<GossipMessage>, watch::Sender<bool>) {
 		let (gossip_persistence_sender, gossip_persistence_receiver) =
 			mpsc::channel::<GossipMessage>(100);
+		let (shutdown_sender, shutdown_receiver) = watch::channel(false);
 		(GossipPersister {
 			gossip_persistence_receiver,
 			server_sync_completion_sender,
-			network_graph
-		}, gossip_persistence_sender)
+			network_graph,
+			shutdown_receiver,
+		}, gossip_persistence_sender, shutdown_sender)
 	}
 
-	pub(crate) async fn persist_gossip(&mut self) {
+	pub(crate) async fn persist_gossip(&mut self) -> Result<(), PersistenceError> {
 		let connection_config = config::db_connection_config();
-		let (client, connection) =
-			connection_config.connect(NoTls).await.unwrap();
-
-		tokio::spawn(async move {
-			if let Err(e) = connection.await {
-				panic!("connection error: {}", e);
-			}
-		});
+		let client = if config::postgres_tls_enabled() {
+			let (client, connection) =
+				connect_with_retry(&connection_config, build_tls_connector()?).await?;
+			tokio::spawn(async move {
+				if let Err(e) = connection.await {
+					eprintln!("connection error: {}", e);
+				}
+			});
+			client
+		} else {
+			let (client, connection) =
+				connect_with_retry(&connection_config, NoTls).await?;
+			tokio::spawn(async move {
+				if let Err(e) = connection.await {
+					eprintln!("connection error: {}", e);
+				}
+			});
+			client
+		};
 
 		{
 			// initialize the database
-			let initialization = client
-				.execute(config::db_config_table_creation_query(), &[])
-				.await;
-			if let Err(initialization_error) = initialization {
-				panic!("db init error: {}", initialization_error);
-			}
+			execute_with_retry(&client, config::db_config_table_creation_query(), &[]).await?;
 
-			let initialization = client
-				.execute(
-					// TODO: figure out a way to fix the id value without Postgres complaining about
-					// its value not being default
-					"INSERT INTO config (id, db_schema) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
-					&[&1, &config::SCHEMA_VERSION]
-				).await;
-			if let Err(initialization_error) = initialization {
-				panic!("db init error: {}", initialization_error);
-			}
+			execute_with_retry(
+				&client,
+				// TODO: figure out a way to fix the id value without Postgres complaining about
+				// its value not being default
+				"INSERT INTO config (id, db_schema) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
+				&[&1, &config::SCHEMA_VERSION]
+			).await?;
 
-			let initialization = client
-				.execute(config::db_announcement_table_creation_query(), &[])
-				.await;
-			if let Err(initialization_error) = initialization {
-				panic!("db init error: {}", initialization_error);
-			}
+			execute_with_retry(&client, config::db_announcement_table_creation_query(), &[]).await?;
 
-			let initialization = client
-				.execute(
-					config::db_channel_update_table_creation_query(),
-					&[],
-				)
-				.await;
-			if let Err(initialization_error) = initialization {
-				panic!("db init error: {}", initialization_error);
-			}
+			execute_with_retry(&client, config::db_channel_update_table_creation_query(), &[]).await?;
 
-			let initialization = client
-				.batch_execute(config::db_index_creation_query())
-				.await;
-			if let Err(initialization_error) = initialization {
-				panic!("db init error: {}", initialization_error);
-			}
+			batch_execute_with_retry(&client, config::db_index_creation_query()).await?;
 		}
 
 		// print log statement every 10,000 messages
@@ -88,74 +280,252 @@ impl GossipPersister {
 		let mut i = 0u32;
 		let mut server_sync_completion_sent = false;
 		let mut latest_graph_cache_time = Instant::now();
+
+		let mut pending_announcements: Vec<ChannelAnnouncement> = Vec::with_capacity(BATCH_SIZE);
+		let mut pending_updates: Vec<ChannelUpdate> = Vec::with_capacity(BATCH_SIZE);
+		let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+		let mut prune_interval = tokio::time::interval(config::prune_interval());
+
+		// set by any DB error that breaks the loop below, so the cleanup block after it still runs
+		// (flushing whatever's left and caching the network graph) before the error is returned
+		let mut loop_result: Result<(), PersistenceError> = Ok(());
+
 		// TODO: it would be nice to have some sort of timeout here so after 10 seconds of
 		// inactivity, some sort of message could be broadcast signaling the activation of request
 		// processing
-		while let Some(gossip_message) = &self.gossip_persistence_receiver.recv().await {
-			i += 1; // count the persisted gossip messages
+		loop {
+			tokio::select! {
+				gossip_message = self.gossip_persistence_receiver.recv() => {
+					let Some(gossip_message) = gossip_message else { break; };
 
-			if i == 1 || i % persistence_log_threshold == 0 {
-				println!("Persisting gossip message #{}", i);
-			}
+					i += 1; // count the persisted gossip messages
 
-			// has it been ten minutes? Just cache it
-			if latest_graph_cache_time.elapsed().as_secs() >= 600 {
-				self.persist_network_graph();
-				latest_graph_cache_time = Instant::now();
-			}
+					if i == 1 || i % persistence_log_threshold == 0 {
+						println!("Persisting gossip message #{}", i);
+					}
+
+					// has it been ten minutes? Just cache it
+					if latest_graph_cache_time.elapsed().as_secs() >= 600 {
+						self.persist_network_graph();
+						latest_graph_cache_time = Instant::now();
+					}
 
-			match &gossip_message {
-				GossipMessage::InitialSyncComplete => {
-					// signal to the server that it may now serve dynamic responses and calculate
-					// snapshots
-					// we take this detour through the persister to ensure that all previous
-					// messages have already been persisted to the database
-					println!("Persister caught up with gossip!");
-					i -= 1; // this wasn't an actual gossip message that needed persisting
-					persistence_log_threshold = 50;
-					if !server_sync_completion_sent {
-						server_sync_completion_sent = true;
-						self.server_sync_completion_sender.send(()).await.unwrap();
-						println!("Server has been notified of persistence completion.");
+					match gossip_message {
+						GossipMessage::InitialSyncComplete => {
+							// signal to the server that it may now serve dynamic responses and calculate
+							// snapshots
+							// we take this detour through the persister to ensure that all previous
+							// messages have already been persisted to the database
+							if let Err(e) = Self::flush_announcements(&client, &mut pending_announcements).await {
+								loop_result = Err(e);
+								break;
+							}
+							if let Err(e) = Self::flush_updates(&client, &mut pending_updates).await {
+								loop_result = Err(e);
+								break;
+							}
+
+							println!("Persister caught up with gossip!");
+							i -= 1; // this wasn't an actual gossip message that needed persisting
+							persistence_log_threshold = 50;
+							if !server_sync_completion_sent {
+								server_sync_completion_sent = true;
+								self.server_sync_completion_sender.send(()).await.unwrap();
+								println!("Server has been notified of persistence completion.");
+							}
+						}
+						GossipMessage::ChannelAnnouncement(announcement) => {
+							pending_announcements.push(announcement);
+							if pending_announcements.len() >= BATCH_SIZE {
+								if let Err(e) = Self::flush_announcements(&client, &mut pending_announcements).await {
+									loop_result = Err(e);
+									break;
+								}
+							}
+						}
+						GossipMessage::ChannelUpdate(update) => {
+							pending_updates.push(update);
+							if pending_updates.len() >= BATCH_SIZE {
+								if let Err(e) = Self::flush_updates(&client, &mut pending_updates).await {
+									loop_result = Err(e);
+									break;
+								}
+							}
+						}
+					}
+				}
+				_ = flush_interval.tick() => {
+					if let Err(e) = Self::flush_announcements(&client, &mut pending_announcements).await {
+						loop_result = Err(e);
+						break;
+					}
+					if let Err(e) = Self::flush_updates(&client, &mut pending_updates).await {
+						loop_result = Err(e);
+						break;
+					}
+				}
+				_ = prune_interval.tick() => {
+					// pruning compares against the network graph's live channel set, which isn't
+					// trustworthy until the initial sync has populated it — skip until then, or a
+					// cold start would see an empty set and wipe every announcement
+					if server_sync_completion_sent {
+						if let Err(e) = self.prune_stale_data(&client).await {
+							loop_result = Err(e);
+							break;
+						}
+					}
+				}
+				Ok(()) = self.shutdown_receiver.changed() => {
+					if *self.shutdown_receiver.borrow() {
+						println!("Persister received shutdown signal, flushing and exiting.");
+						break;
 					}
 				}
-				GossipMessage::ChannelAnnouncement(announcement) => {
+			}
+		}
+
+		// make sure nothing is left buffered once we stop, whether the channel closed, we were
+		// asked to shut down, or a DB error broke the loop above. This is best-effort when a DB
+		// error already broke the loop — the connection may be unusable — but it's worth one more
+		// try so the sibling buffer isn't also silently dropped; either way `loop_result` keeps the
+		// original error rather than being overwritten by a second failure here.
+		if let Err(e) = Self::flush_announcements(&client, &mut pending_announcements).await {
+			eprintln!("failed to flush pending channel announcements while exiting: {}", e);
+			if loop_result.is_ok() {
+				loop_result = Err(e);
+			}
+		}
+		if let Err(e) = Self::flush_updates(&client, &mut pending_updates).await {
+			eprintln!("failed to flush pending channel updates while exiting: {}", e);
+			if loop_result.is_ok() {
+				loop_result = Err(e);
+			}
+		}
+		self.persist_network_graph();
+
+		loop_result
+	}
+
+	/// Known limitation: on a connection-level error (see `is_row_level`) the batch has already
+	/// been taken out of `pending_announcements`, so it is lost once the error propagates out of
+	/// `persist_gossip` rather than being replayed after the persister reconnects.
+	async fn flush_announcements(client: &tokio_postgres::Client, pending_announcements: &mut Vec<ChannelAnnouncement>) -> Result<(), PersistenceError> {
+		if pending_announcements.is_empty() {
+			return Ok(());
+		}
+
+		let batch = std::mem::take(pending_announcements);
+		Self::insert_announcements(client, &batch).await
+	}
 
+	/// Inserts `batch` as a single multi-row statement, bisecting on a row-level failure; see
+	/// `insert_batch_with_bisection`.
+	async fn insert_announcements(client: &tokio_postgres::Client, batch: &[ChannelAnnouncement]) -> Result<(), PersistenceError> {
+		let try_insert = move |batch: &[ChannelAnnouncement]| -> InsertFuture<'_> {
+			Box::pin(async move {
+				let mut query = String::from("INSERT INTO channel_announcements (\
+					short_channel_id, \
+					block_height, \
+					chain_hash, \
+					announcement_signed \
+				) VALUES ");
+
+				let mut scids_hex = Vec::with_capacity(batch.len());
+				let mut block_heights = Vec::with_capacity(batch.len());
+				let mut chain_hashes_hex = Vec::with_capacity(batch.len());
+				let mut announcements_signed = Vec::with_capacity(batch.len());
+
+				for announcement in batch.iter() {
 					let scid = announcement.contents.short_channel_id;
-					let scid_hex = hex_utils::hex_str(&scid.to_be_bytes());
+					scids_hex.push(hex_utils::hex_str(&scid.to_be_bytes()));
 					// scid is 8 bytes
 					// block height is the first three bytes
 					// to obtain block height, shift scid right by 5 bytes (40 bits)
-					let block_height = (scid >> 5 * 8) as i32;
-					let chain_hash = announcement.contents.chain_hash.as_ref();
-					let chain_hash_hex = hex_utils::hex_str(chain_hash);
+					block_heights.push((scid >> 5 * 8) as i32);
+					chain_hashes_hex.push(hex_utils::hex_str(announcement.contents.chain_hash.as_ref()));
 
 					// start with the type prefix, which is already known a priori
 					let mut announcement_signed = Vec::new(); // vec![1, 0];
 					announcement.write(&mut announcement_signed).unwrap();
+					announcements_signed.push(announcement_signed);
+				}
 
-					let result = client
-						.execute("INSERT INTO channel_announcements (\
-							short_channel_id, \
-							block_height, \
-							chain_hash, \
-							announcement_signed \
-						) VALUES ($1, $2, $3, $4) ON CONFLICT (short_channel_id) DO NOTHING", &[
-							&scid_hex,
-							&block_height,
-							&chain_hash_hex,
-							&announcement_signed
-						]).await;
-					if result.is_err() {
-						panic!("error: {}", result.err().unwrap());
+				let mut params: Vec<&(dyn ToSql + Sync)> = Vec::with_capacity(batch.len() * 4);
+				for row in 0..batch.len() {
+					if row > 0 {
+						query.push(',');
 					}
+					let base = row * 4;
+					query.push_str(&format!(" (${}, ${}, ${}, ${})", base + 1, base + 2, base + 3, base + 4));
+					params.push(&scids_hex[row]);
+					params.push(&block_heights[row]);
+					params.push(&chain_hashes_hex[row]);
+					params.push(&announcements_signed[row]);
 				}
-				GossipMessage::ChannelUpdate(update) => {
+				query.push_str(" ON CONFLICT (short_channel_id) DO NOTHING");
+
+				execute_with_retry(client, query.as_str(), &params).await
+			})
+		};
+		let describe = |announcement: &ChannelAnnouncement| {
+			format!("channel announcement (scid {})", hex_utils::hex_str(&announcement.contents.short_channel_id.to_be_bytes()))
+		};
+
+		insert_batch_with_bisection(batch, &try_insert, &describe).await
+	}
+
+	/// Known limitation: on a connection-level error (see `is_row_level`) the batch has already
+	/// been taken out of `pending_updates`, so it is lost once the error propagates out of
+	/// `persist_gossip` rather than being replayed after the persister reconnects.
+	async fn flush_updates(client: &tokio_postgres::Client, pending_updates: &mut Vec<ChannelUpdate>) -> Result<(), PersistenceError> {
+		if pending_updates.is_empty() {
+			return Ok(());
+		}
+
+		let batch = std::mem::take(pending_updates);
+		Self::insert_updates(client, &batch).await
+	}
+
+	/// Inserts `batch` as a single multi-row statement, bisecting on a row-level failure; see
+	/// `insert_batch_with_bisection`.
+	async fn insert_updates(client: &tokio_postgres::Client, batch: &[ChannelUpdate]) -> Result<(), PersistenceError> {
+		let try_insert = move |batch: &[ChannelUpdate]| -> InsertFuture<'_> {
+			Box::pin(async move {
+				let mut query = String::from("INSERT INTO channel_updates (\
+					composite_index, \
+					chain_hash, \
+					short_channel_id, \
+					timestamp, \
+					channel_flags, \
+					direction, \
+					disable, \
+					cltv_expiry_delta, \
+					htlc_minimum_msat, \
+					fee_base_msat, \
+					fee_proportional_millionths, \
+					htlc_maximum_msat, \
+					blob_signed \
+				) VALUES ");
+
+				let mut composite_indexes = Vec::with_capacity(batch.len());
+				let mut chain_hashes_hex = Vec::with_capacity(batch.len());
+				let mut scids_hex = Vec::with_capacity(batch.len());
+				let mut timestamps = Vec::with_capacity(batch.len());
+				let mut channel_flags_vec = Vec::with_capacity(batch.len());
+				let mut directions = Vec::with_capacity(batch.len());
+				let mut disables = Vec::with_capacity(batch.len());
+				let mut cltv_expiry_deltas = Vec::with_capacity(batch.len());
+				let mut htlc_minimum_msats = Vec::with_capacity(batch.len());
+				let mut fee_base_msats = Vec::with_capacity(batch.len());
+				let mut fee_proportional_millionths_vec = Vec::with_capacity(batch.len());
+				let mut htlc_maximum_msats = Vec::with_capacity(batch.len());
+				let mut blobs_signed = Vec::with_capacity(batch.len());
+
+				for update in batch.iter() {
 					let scid = update.contents.short_channel_id;
 					let scid_hex = hex_utils::hex_str(&scid.to_be_bytes());
 
-					let chain_hash = update.contents.chain_hash.as_ref();
-					let chain_hash_hex = hex_utils::hex_str(chain_hash);
+					let chain_hash_hex = hex_utils::hex_str(update.contents.chain_hash.as_ref());
 
 					let timestamp = update.contents.timestamp as i64;
 
@@ -163,55 +533,113 @@ impl GossipPersister {
 					let direction = channel_flags & 1;
 					let disable = (channel_flags & 2) > 0;
 
-					let composite_index = format!("{}:{}:{}", scid_hex, timestamp, direction);
-
-					let cltv_expiry_delta = update.contents.cltv_expiry_delta as i32;
-					let htlc_minimum_msat = update.contents.htlc_minimum_msat as i64;
-					let fee_base_msat = update.contents.fee_base_msat as i32;
-					let fee_proportional_millionths =
-						update.contents.fee_proportional_millionths as i32;
-					let htlc_maximum_msat = update.contents.htlc_maximum_msat as i64;
+					composite_indexes.push(format!("{}:{}:{}", scid_hex, timestamp, direction));
+					chain_hashes_hex.push(chain_hash_hex);
+					scids_hex.push(scid_hex);
+					timestamps.push(timestamp);
+					channel_flags_vec.push(channel_flags);
+					directions.push(direction);
+					disables.push(disable);
+					cltv_expiry_deltas.push(update.contents.cltv_expiry_delta as i32);
+					htlc_minimum_msats.push(update.contents.htlc_minimum_msat as i64);
+					fee_base_msats.push(update.contents.fee_base_msat as i32);
+					fee_proportional_millionths_vec.push(update.contents.fee_proportional_millionths as i32);
+					htlc_maximum_msats.push(update.contents.htlc_maximum_msat as i64);
 
 					// start with the type prefix, which is already known a priori
 					let mut update_signed = Vec::new(); // vec![1, 2];
 					update.write(&mut update_signed).unwrap();
+					blobs_signed.push(update_signed);
+				}
 
-					let result = client
-						.execute("INSERT INTO channel_updates (\
-							composite_index, \
-							chain_hash, \
-							short_channel_id, \
-							timestamp, \
-							channel_flags, \
-							direction, \
-							disable, \
-							cltv_expiry_delta, \
-							htlc_minimum_msat, \
-							fee_base_msat, \
-							fee_proportional_millionths, \
-							htlc_maximum_msat, \
-							blob_signed \
-						) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)  ON CONFLICT (composite_index) DO NOTHING", &[
-							&composite_index,
-							&chain_hash_hex,
-							&scid_hex,
-							&timestamp,
-							&channel_flags,
-							&direction,
-							&disable,
-							&cltv_expiry_delta,
-							&htlc_minimum_msat,
-							&fee_base_msat,
-							&fee_proportional_millionths,
-							&htlc_maximum_msat,
-							&update_signed
-						]).await;
-					if result.is_err() {
-						panic!("error: {}", result.err().unwrap());
+				let mut params: Vec<&(dyn ToSql + Sync)> = Vec::with_capacity(batch.len() * 13);
+				for row in 0..batch.len() {
+					if row > 0 {
+						query.push(',');
 					}
+					let base = row * 13;
+					query.push_str(&format!(
+						" (${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${})",
+						base + 1, base + 2, base + 3, base + 4, base + 5, base + 6, base + 7,
+						base + 8, base + 9, base + 10, base + 11, base + 12, base + 13
+					));
+					params.push(&composite_indexes[row]);
+					params.push(&chain_hashes_hex[row]);
+					params.push(&scids_hex[row]);
+					params.push(&timestamps[row]);
+					params.push(&channel_flags_vec[row]);
+					params.push(&directions[row]);
+					params.push(&disables[row]);
+					params.push(&cltv_expiry_deltas[row]);
+					params.push(&htlc_minimum_msats[row]);
+					params.push(&fee_base_msats[row]);
+					params.push(&fee_proportional_millionths_vec[row]);
+					params.push(&htlc_maximum_msats[row]);
+					params.push(&blobs_signed[row]);
 				}
-			}
+				query.push_str(" ON CONFLICT (composite_index) DO NOTHING");
+
+				execute_with_retry(client, query.as_str(), &params).await
+			})
+		};
+		let describe = |update: &ChannelUpdate| {
+			let scid_hex = hex_utils::hex_str(&update.contents.short_channel_id.to_be_bytes());
+			let direction = (update.contents.flags as i32) & 1;
+			format!("channel update (composite_index {}:{}:{})", scid_hex, update.contents.timestamp, direction)
+		};
+
+		insert_batch_with_bisection(batch, &try_insert, &describe).await
+	}
+
+	/// Deletes `channel_updates` rows that are both superseded and older than
+	/// `config::prune_retention_seconds()`, and announcements for channels that have since
+	/// fallen out of the network graph, keeping the Postgres footprint bounded over long-running
+	/// deployments. Always keeps the latest update per `(short_channel_id, direction)`.
+	async fn prune_stale_data(&self, client: &tokio_postgres::Client) -> Result<(), PersistenceError> {
+		println!("Pruning superseded channel updates…");
+
+		let now = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap()
+			.as_secs() as i64;
+		let retention_cutoff = now - config::prune_retention_seconds();
+
+		let pruned_updates = execute_with_retry(
+			client,
+			"DELETE FROM channel_updates a \
+			USING channel_updates b \
+			WHERE a.short_channel_id = b.short_channel_id \
+			AND a.direction = b.direction \
+			AND a.timestamp < b.timestamp \
+			AND a.timestamp < $1",
+			&[&retention_cutoff],
+		).await?;
+		println!("Pruned {} superseded channel update(s)", pruned_updates);
+
+		// now that the network graph has had a chance to drop stale channels, remove the
+		// announcements for any channel it no longer knows about
+		self.network_graph.remove_stale_channels();
+		let live_scids: Vec<String> = self.network_graph.read_only().channels()
+			.unordered_iter()
+			.map(|(scid, _)| hex_utils::hex_str(&scid.to_be_bytes()))
+			.collect();
+
+		// belt-and-suspenders: an empty live set would make `NOT (short_channel_id = ANY($1))`
+		// match every row and wipe the table, so only prune announcements once we actually know
+		// of live channels
+		if live_scids.is_empty() {
+			println!("Network graph has no live channels yet, skipping announcement pruning");
+			return Ok(());
 		}
+
+		let pruned_announcements = execute_with_retry(
+			client,
+			"DELETE FROM channel_announcements WHERE NOT (short_channel_id = ANY($1))",
+			&[&live_scids],
+		).await?;
+		println!("Pruned {} stale channel announcement(s)", pruned_announcements);
+
+		Ok(())
 	}
 
 	fn persist_network_graph(&self) {